@@ -1,5 +1,6 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::net::SocketAddr;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Connection {
@@ -19,13 +20,32 @@ impl fmt::Display for Connection {
     }
 }
 
+/// A slot tentatively claimed by `ConnectionList::reserve` on behalf of
+/// `remote`. Counts against both the pool size and `remote`'s per-remote
+/// cap until it is turned into a live `Connection` with `promote`, or given
+/// back with `release`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Reservation {
+    id: usize,
+    generation: usize,
+    remote: SocketAddr,
+}
+
 pub struct ConnectionList {
     connections: Vec<Connection>,
     empty: VecDeque<usize>,
+    max_per_remote: usize,
+    max_waiters: usize,
+    /// Remote owning each claimed (reserved or promoted) slot, so releasing
+    /// or deleting a connection can find its per-remote count to decrement.
+    owners: HashMap<usize, SocketAddr>,
+    per_remote_counts: HashMap<SocketAddr, usize>,
+    waiters: VecDeque<SocketAddr>,
+    ready_waiters: VecDeque<(SocketAddr, Reservation)>,
 }
 
 impl ConnectionList {
-    pub fn new(size: usize) -> Self {
+    pub fn new(size: usize, max_per_remote: usize, max_waiters: usize) -> Self {
         let mut connections = Vec::with_capacity(size);
         let mut empty = VecDeque::with_capacity(size);
         for i in 0..size {
@@ -33,7 +53,16 @@ impl ConnectionList {
             empty.push_back(i);
         }
 
-        Self { connections, empty }
+        Self {
+            connections,
+            empty,
+            max_per_remote,
+            max_waiters,
+            owners: HashMap::new(),
+            per_remote_counts: HashMap::new(),
+            waiters: VecDeque::new(),
+            ready_waiters: VecDeque::new(),
+        }
     }
 
     pub fn is_alive(&self, connection: Connection) -> bool {
@@ -46,6 +75,8 @@ impl ConnectionList {
         connection.generation % 2 == 1
     }
 
+    /// Pops the next free slot with no admission control at all. Prefer
+    /// `reserve`/`promote` so a single remote can't exhaust the pool.
     pub fn create_connection(&mut self) -> Option<Connection> {
         let id = self.empty.pop_front()?;
 
@@ -68,10 +99,124 @@ impl ConnectionList {
 
         let new_connection = Connection::new(id, old_connection.generation + 1);
         self.connections[id] = new_connection;
-        self.empty.push_back(id);
+        self.unclaim(id);
+        self.free_slot(id);
 
         Ok(())
     }
+
+    /// Tentatively claims a slot for `remote`, counting against both the
+    /// pool size and `remote`'s per-remote cap. When the pool is full,
+    /// parks `remote` in a bounded FIFO waiter queue instead of failing
+    /// outright; poll `poll_ready_waiter` to learn when a freed slot was
+    /// handed to a parked remote.
+    pub fn reserve(&mut self, remote: SocketAddr) -> Option<Reservation> {
+        if self.remote_count(remote) >= self.max_per_remote {
+            return None;
+        }
+
+        match self.empty.pop_front() {
+            Some(id) => Some(self.claim(id, remote)),
+            None => {
+                // a remote already parked doesn't get a second ticket, so it
+                // holds at most one place in the bounded waiter queue
+                if self.waiters.len() < self.max_waiters && !self.waiters.contains(&remote) {
+                    self.waiters.push_back(remote);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Turns a still-valid reservation into a live `Connection`.
+    pub fn promote(&mut self, reservation: Reservation) -> Option<Connection> {
+        let id = reservation.id;
+
+        if self.connections[id].generation != reservation.generation {
+            return None;
+        }
+
+        let new_connection = Connection::new(id, reservation.generation + 1);
+        self.connections[id] = new_connection;
+
+        Some(new_connection)
+    }
+
+    /// Gives back a reservation that was never promoted.
+    pub fn release(&mut self, reservation: Reservation) {
+        let id = reservation.id;
+
+        if self.connections[id].generation != reservation.generation {
+            return;
+        }
+
+        // bump past the reservation's generation (staying even, i.e. not
+        // alive) so a stale `Reservation` still held by the caller can no
+        // longer pass `promote`'s generation check, even though the slot
+        // itself may already have been handed to someone else
+        self.connections[id] = Connection::new(id, reservation.generation + 2);
+
+        self.unclaim(id);
+        self.free_slot(id);
+    }
+
+    /// Pops the next remote that was handed a freed slot while parked in
+    /// the waiter queue, along with its reservation. The caller is expected
+    /// to `promote` or `release` it like any other reservation.
+    pub fn poll_ready_waiter(&mut self) -> Option<(SocketAddr, Reservation)> {
+        self.ready_waiters.pop_front()
+    }
+
+    fn remote_count(&self, remote: SocketAddr) -> usize {
+        self.per_remote_counts.get(&remote).copied().unwrap_or(0)
+    }
+
+    fn claim(&mut self, id: usize, remote: SocketAddr) -> Reservation {
+        let generation = self.connections[id].generation;
+
+        self.owners.insert(id, remote);
+        *self.per_remote_counts.entry(remote).or_insert(0) += 1;
+
+        Reservation {
+            id,
+            generation,
+            remote,
+        }
+    }
+
+    fn unclaim(&mut self, id: usize) {
+        let remote = match self.owners.remove(&id) {
+            Some(remote) => remote,
+            None => return,
+        };
+
+        if let Some(count) = self.per_remote_counts.get_mut(&remote) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_remote_counts.remove(&remote);
+            }
+        }
+    }
+
+    /// Hands a just-freed slot straight to the longest-waiting remote that
+    /// is still under its per-remote cap, or returns it to the free pool if
+    /// nobody qualifies.
+    fn free_slot(&mut self, id: usize) {
+        while let Some(remote) = self.waiters.pop_front() {
+            if self.remote_count(remote) >= self.max_per_remote {
+                // this remote reached its cap some other way while parked;
+                // drop its stale ticket and try the next waiter
+                continue;
+            }
+
+            let reservation = self.claim(id, remote);
+            self.ready_waiters.push_back((remote, reservation));
+            return;
+        }
+
+        self.empty.push_back(id);
+    }
 }
 
 pub struct ConnectionIterator<'a> {
@@ -181,3 +326,85 @@ impl<T> ConnectionDataList<T> {
         self.items[id].take()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn reserve_then_promote_yields_a_live_connection() {
+        let mut connections = ConnectionList::new(2, 4, 4);
+
+        let reservation = connections.reserve(addr(1)).unwrap();
+        let conn = connections.promote(reservation).unwrap();
+
+        assert!(connections.is_alive(conn));
+    }
+
+    #[test]
+    fn released_reservation_can_no_longer_be_promoted() {
+        let mut connections = ConnectionList::new(2, 4, 4);
+
+        let reservation = connections.reserve(addr(1)).unwrap();
+        connections.release(reservation);
+
+        assert!(connections.promote(reservation).is_none());
+    }
+
+    #[test]
+    fn a_stale_handle_cannot_promote_a_slot_handed_to_a_newer_reservation() {
+        let mut connections = ConnectionList::new(1, 4, 4);
+
+        let first = connections.reserve(addr(1)).unwrap();
+        connections.release(first);
+
+        let second = connections.reserve(addr(2)).unwrap();
+
+        assert!(connections.promote(first).is_none());
+        assert!(connections.promote(second).is_some());
+    }
+
+    #[test]
+    fn per_remote_cap_rejects_further_reservations() {
+        let mut connections = ConnectionList::new(4, 1, 4);
+
+        assert!(connections.reserve(addr(1)).is_some());
+        assert!(connections.reserve(addr(1)).is_none());
+    }
+
+    #[test]
+    fn a_parked_remote_is_not_queued_twice() {
+        let mut connections = ConnectionList::new(1, 4, 4);
+
+        let first = connections.reserve(addr(1)).unwrap();
+        assert!(connections.reserve(addr(2)).is_none());
+        assert!(connections.reserve(addr(2)).is_none());
+
+        connections.release(first);
+
+        let (remote, _) = connections.poll_ready_waiter().unwrap();
+        assert_eq!(remote, addr(2));
+        assert!(connections.poll_ready_waiter().is_none());
+    }
+
+    #[test]
+    fn free_slot_skips_a_waiter_that_is_already_at_its_cap() {
+        let mut connections = ConnectionList::new(2, 1, 4);
+
+        // the public API can't produce a queued waiter that's already at
+        // its cap, so this directly engineers that state to check the
+        // defensive recheck in `free_slot`
+        connections.per_remote_counts.insert(addr(1), 1);
+        connections.waiters.push_back(addr(1));
+        connections.waiters.push_back(addr(2));
+
+        connections.free_slot(0);
+
+        let (remote, _) = connections.poll_ready_waiter().unwrap();
+        assert_eq!(remote, addr(2));
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::connection::{Connection, ConnectionDataList};
+use crate::rng;
+
+/// Connection ids are opaque byte strings of at most this many bytes,
+/// matching the QUIC wire limit.
+pub const MAX_CONNECTION_ID_LEN: usize = 20;
+
+/// Default number of connection ids a single connection may have active at
+/// once.
+pub const DEFAULT_ACTIVE_CID_LIMIT: usize = 8;
+
+/// An opaque, randomly generated identifier used to demux inbound packets
+/// instead of the internal connection slot index. A zero-length id is a
+/// valid special case.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ConnectionId {
+    bytes: [u8; MAX_CONNECTION_ID_LEN],
+    len: u8,
+}
+
+impl ConnectionId {
+    pub fn new(bytes: &[u8]) -> Self {
+        assert!(bytes.len() <= MAX_CONNECTION_ID_LEN, "connection id too long");
+
+        let mut buf = [0u8; MAX_CONNECTION_ID_LEN];
+        buf[..bytes.len()].copy_from_slice(bytes);
+
+        Self {
+            bytes: buf,
+            len: bytes.len() as u8,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Generates a fresh id with a greased length in `8..=20` bytes.
+    fn generate() -> Self {
+        let len = 8 + (rng::next_byte() as usize % (MAX_CONNECTION_ID_LEN - 8 + 1));
+
+        let mut bytes = [0u8; MAX_CONNECTION_ID_LEN];
+        rng::fill(&mut bytes[..len]);
+
+        Self::new(&bytes[..len])
+    }
+}
+
+/// Inline, capacity-bounded set of connection ids owned by a single
+/// connection, so the common case of a handful of active ids needs no heap
+/// allocation.
+#[derive(Clone, Copy)]
+struct CidSet {
+    entries: [Option<(u64, ConnectionId)>; DEFAULT_ACTIVE_CID_LIMIT],
+    count: usize,
+    next_seqno: u64,
+}
+
+impl CidSet {
+    fn new() -> Self {
+        Self {
+            entries: [None; DEFAULT_ACTIVE_CID_LIMIT],
+            count: 0,
+            next_seqno: 0,
+        }
+    }
+
+    fn push(&mut self, cid: ConnectionId) -> Option<(u64, ConnectionId)> {
+        if self.count >= self.entries.len() {
+            return None;
+        }
+
+        let seqno = self.next_seqno;
+        self.next_seqno += 1;
+
+        for slot in self.entries.iter_mut() {
+            if slot.is_none() {
+                *slot = Some((seqno, cid));
+                self.count += 1;
+                return Some((seqno, cid));
+            }
+        }
+
+        None
+    }
+
+    fn remove(&mut self, seqno: u64) -> Option<ConnectionId> {
+        for slot in self.entries.iter_mut() {
+            if let Some((s, cid)) = *slot {
+                if s == seqno {
+                    *slot = None;
+                    self.count -= 1;
+                    return Some(cid);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(u64, ConnectionId)> {
+        self.entries.iter().filter_map(|e| e.as_ref())
+    }
+}
+
+/// Reverse index from opaque connection ids to the live `Connection` they
+/// currently route to, modeling QUIC's NEW_CONNECTION_ID / RETIRE_CONNECTION_ID
+/// behavior. Inbound packets are demuxed by looking up the id here rather
+/// than by the internal slot index, which lets a connection rotate its
+/// routable identifier without losing its session state.
+pub struct ConnectionIdMap {
+    sets: ConnectionDataList<CidSet>,
+    by_cid: HashMap<ConnectionId, Connection>,
+    active_limit: usize,
+}
+
+impl ConnectionIdMap {
+    pub fn new(size: usize) -> Self {
+        Self::with_active_limit(size, DEFAULT_ACTIVE_CID_LIMIT)
+    }
+
+    pub fn with_active_limit(size: usize, active_limit: usize) -> Self {
+        Self {
+            sets: ConnectionDataList::new(size),
+            by_cid: HashMap::new(),
+            active_limit: active_limit.min(DEFAULT_ACTIVE_CID_LIMIT),
+        }
+    }
+
+    /// Issues a fresh connection id for `connection`, returning its sequence
+    /// number. Returns `None` once `active_limit` ids are already live for
+    /// this connection.
+    pub fn issue_cid(&mut self, connection: Connection) -> Option<(u64, ConnectionId)> {
+        if self.sets.get(connection).is_none() {
+            self.sets.set(connection, CidSet::new());
+        }
+
+        let set = self.sets.get_mut(connection)?;
+        if set.count >= self.active_limit {
+            return None;
+        }
+
+        let cid = ConnectionId::generate();
+        let issued = set.push(cid)?;
+        self.by_cid.insert(cid, connection);
+
+        Some(issued)
+    }
+
+    pub fn lookup(&self, cid: &ConnectionId) -> Option<Connection> {
+        self.by_cid.get(cid).copied()
+    }
+
+    /// Retires a single connection id by sequence number, freeing its slot
+    /// so the owner may issue a replacement.
+    pub fn retire_cid(&mut self, connection: Connection, seqno: u64) -> Result<(), ()> {
+        let set = self.sets.get_mut(connection).ok_or(())?;
+        let cid = set.remove(seqno).ok_or(())?;
+        self.by_cid.remove(&cid);
+
+        Ok(())
+    }
+
+    /// Retires every connection id owned by `connection`. Must be called
+    /// alongside `ConnectionList::delete_connection` so a bumped generation
+    /// never leaves stale entries behind in the reverse index.
+    pub fn retire_all(&mut self, connection: Connection) {
+        if let Some(set) = self.sets.remove(connection) {
+            for (_, cid) in set.iter() {
+                self.by_cid.remove(cid);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionList;
+
+    #[test]
+    fn issued_cid_looks_up_to_its_connection() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut cids = ConnectionIdMap::new(4);
+
+        let conn = connections.create_connection().unwrap();
+        let (seqno, cid) = cids.issue_cid(conn).unwrap();
+
+        assert_eq!(seqno, 0);
+        assert_eq!(cids.lookup(&cid), Some(conn));
+    }
+
+    #[test]
+    fn active_limit_is_enforced_per_connection() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut cids = ConnectionIdMap::with_active_limit(4, 2);
+
+        let conn = connections.create_connection().unwrap();
+
+        assert!(cids.issue_cid(conn).is_some());
+        assert!(cids.issue_cid(conn).is_some());
+        assert!(cids.issue_cid(conn).is_none());
+    }
+
+    #[test]
+    fn retire_cid_frees_the_reverse_mapping_but_not_other_cids() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut cids = ConnectionIdMap::new(4);
+
+        let conn = connections.create_connection().unwrap();
+        let (seqno_a, cid_a) = cids.issue_cid(conn).unwrap();
+        let (_, cid_b) = cids.issue_cid(conn).unwrap();
+
+        cids.retire_cid(conn, seqno_a).unwrap();
+
+        assert_eq!(cids.lookup(&cid_a), None);
+        assert_eq!(cids.lookup(&cid_b), Some(conn));
+    }
+
+    #[test]
+    fn retire_all_clears_every_cid_owned_by_the_connection() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut cids = ConnectionIdMap::new(4);
+
+        let conn = connections.create_connection().unwrap();
+        let (_, cid_a) = cids.issue_cid(conn).unwrap();
+        let (_, cid_b) = cids.issue_cid(conn).unwrap();
+
+        cids.retire_all(conn);
+
+        assert_eq!(cids.lookup(&cid_a), None);
+        assert_eq!(cids.lookup(&cid_b), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::net::SocketAddr;
+
+use crate::connection::{Connection, ConnectionDataList};
+use crate::rng;
+
+/// Length in bytes of a PATH_CHALLENGE / PATH_RESPONSE token.
+pub const PATH_CHALLENGE_LEN: usize = 8;
+
+/// A random path-challenge token the peer must echo back before we trust a
+/// candidate address.
+pub type PathChallengeToken = [u8; PATH_CHALLENGE_LEN];
+
+struct PendingMigration {
+    candidate: SocketAddr,
+    token: PathChallengeToken,
+    retransmits: usize,
+    bytes_received: usize,
+    bytes_sent: usize,
+}
+
+impl PendingMigration {
+    fn new(candidate: SocketAddr) -> Self {
+        let mut token = [0u8; PATH_CHALLENGE_LEN];
+        rng::fill(&mut token);
+
+        Self {
+            candidate,
+            token,
+            retransmits: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
+        }
+    }
+}
+
+/// Tracks in-flight path validation for connections that have seen a packet
+/// arrive bearing a known connection id but from an unfamiliar remote
+/// address. The stored address is only a *candidate*: the connection keeps
+/// serving its existing path until the peer echoes our challenge token back
+/// in a path-response.
+pub struct PathValidator {
+    pending: ConnectionDataList<PendingMigration>,
+    max_challenge_retransmits: usize,
+}
+
+impl PathValidator {
+    pub fn new(size: usize, max_challenge_retransmits: usize) -> Self {
+        Self {
+            pending: ConnectionDataList::new(size),
+            max_challenge_retransmits,
+        }
+    }
+
+    /// Starts validating `new_addr` as a candidate path for `connection`,
+    /// returning the challenge token to send. Overwrites any
+    /// already-in-flight migration for the connection.
+    pub fn begin_migration(
+        &mut self,
+        connection: Connection,
+        new_addr: SocketAddr,
+    ) -> PathChallengeToken {
+        let migration = PendingMigration::new(new_addr);
+        let token = migration.token;
+        self.pending.set(connection, migration);
+
+        token
+    }
+
+    /// Called when a path-response carrying `token` arrives. Returns the
+    /// validated address to promote to the connection's active path, and
+    /// clears the pending migration. Returns `None` on a stale or mismatched
+    /// token so the old path keeps being served.
+    pub fn on_path_response(
+        &mut self,
+        connection: Connection,
+        token: PathChallengeToken,
+    ) -> Option<SocketAddr> {
+        let matches = self
+            .pending
+            .get(connection)
+            .map_or(false, |migration| migration.token == token);
+
+        if !matches {
+            return None;
+        }
+
+        self.pending.remove(connection).map(|migration| migration.candidate)
+    }
+
+    pub fn is_validating(&self, connection: Connection) -> bool {
+        self.pending.get(connection).is_some()
+    }
+
+    /// Anti-amplification check: while validation is pending we must never
+    /// send more than 3x the bytes we've received on the unvalidated path.
+    /// Returns `false` if sending `len` more bytes would exceed that limit.
+    pub fn can_send(&self, connection: Connection, len: usize) -> bool {
+        match self.pending.get(connection) {
+            Some(migration) => migration.bytes_sent + len <= migration.bytes_received * 3,
+            None => true,
+        }
+    }
+
+    pub fn on_bytes_sent(&mut self, connection: Connection, len: usize) {
+        if let Some(migration) = self.pending.get_mut(connection) {
+            migration.bytes_sent += len;
+        }
+    }
+
+    pub fn on_bytes_received(&mut self, connection: Connection, len: usize) {
+        if let Some(migration) = self.pending.get_mut(connection) {
+            migration.bytes_received += len;
+        }
+    }
+
+    /// Called once per update for a connection with a migration in flight,
+    /// on the same cadence as the `heartbeat`/`timeout` tick engine. Returns
+    /// `true` if the challenge should be retransmitted, or `false` once the
+    /// `max_challenge_retransmits` budget is spent, at which point the
+    /// candidate path is abandoned.
+    pub fn on_tick(&mut self, connection: Connection) -> bool {
+        let migration = match self.pending.get_mut(connection) {
+            Some(migration) => migration,
+            None => return false,
+        };
+
+        if migration.retransmits >= self.max_challenge_retransmits {
+            self.pending.remove(connection);
+            return false;
+        }
+
+        migration.retransmits += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionList;
+
+    fn candidate_addr() -> SocketAddr {
+        "127.0.0.1:9000".parse().unwrap()
+    }
+
+    #[test]
+    fn mismatched_token_is_rejected_and_keeps_validating() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut validator = PathValidator::new(4, 3);
+
+        let conn = connections.create_connection().unwrap();
+        validator.begin_migration(conn, candidate_addr());
+
+        assert_eq!(validator.on_path_response(conn, [0u8; PATH_CHALLENGE_LEN]), None);
+        assert!(validator.is_validating(conn));
+    }
+
+    #[test]
+    fn matching_token_validates_and_clears_pending_state() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut validator = PathValidator::new(4, 3);
+
+        let conn = connections.create_connection().unwrap();
+        let token = validator.begin_migration(conn, candidate_addr());
+
+        assert_eq!(validator.on_path_response(conn, token), Some(candidate_addr()));
+        assert!(!validator.is_validating(conn));
+    }
+
+    #[test]
+    fn anti_amplification_caps_sends_at_3x_received() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut validator = PathValidator::new(4, 3);
+
+        let conn = connections.create_connection().unwrap();
+        validator.begin_migration(conn, candidate_addr());
+        validator.on_bytes_received(conn, 100);
+
+        assert!(validator.can_send(conn, 300));
+        assert!(!validator.can_send(conn, 301));
+
+        validator.on_bytes_sent(conn, 300);
+        assert!(!validator.can_send(conn, 1));
+    }
+
+    #[test]
+    fn tick_abandons_migration_once_retransmit_budget_is_spent() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut validator = PathValidator::new(4, 2);
+
+        let conn = connections.create_connection().unwrap();
+        validator.begin_migration(conn, candidate_addr());
+
+        assert!(validator.on_tick(conn));
+        assert!(validator.on_tick(conn));
+        assert!(!validator.on_tick(conn));
+        assert!(!validator.is_validating(conn));
+    }
+}
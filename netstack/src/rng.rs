@@ -0,0 +1,23 @@
+//! A tiny, dependency-free source of unpredictable bytes used for greasing
+//! connection ids and path validation tokens. Not cryptographically secure,
+//! just unguessable enough to resist casual off-path spoofing.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_byte() -> u8 {
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(counter);
+    (hasher.finish() & 0xff) as u8
+}
+
+pub(crate) fn fill(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        *b = next_byte();
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use crate::connection::{Connection, ConnectionDataList};
+use crate::connection_id::ConnectionId;
+
+/// Width of the Kademlia-style keyspace routing operates over.
+pub const KEY_LEN: usize = 32;
+const NUM_BUCKETS: usize = KEY_LEN * 8;
+
+/// A point in the 256-bit routing keyspace, derived from a connection id.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NodeKey([u8; KEY_LEN]);
+
+impl NodeKey {
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Derives a routing key by hashing a connection id out to the full
+    /// 256-bit keyspace, since ids themselves may be shorter.
+    pub fn from_connection_id(cid: &ConnectionId) -> Self {
+        let mut key = [0u8; KEY_LEN];
+
+        for (i, chunk) in key.chunks_mut(8).enumerate() {
+            let mut hasher = DefaultHasher::new();
+            i.hash(&mut hasher);
+            cid.as_slice().hash(&mut hasher);
+
+            chunk.copy_from_slice(&hasher.finish().to_be_bytes()[..chunk.len()]);
+        }
+
+        Self(key)
+    }
+
+    fn distance(&self, other: &NodeKey) -> [u8; KEY_LEN] {
+        let mut out = [0u8; KEY_LEN];
+        for i in 0..KEY_LEN {
+            out[i] = self.0[i] ^ other.0[i];
+        }
+
+        out
+    }
+}
+
+fn leading_zero_bits(distance: &[u8; KEY_LEN]) -> usize {
+    for (i, byte) in distance.iter().enumerate() {
+        if *byte != 0 {
+            return i * 8 + byte.leading_zeros() as usize;
+        }
+    }
+
+    KEY_LEN * 8
+}
+
+struct Peer {
+    connection: Connection,
+    key: NodeKey,
+}
+
+/// A single Kademlia k-bucket: peers whose distance to our own key shares
+/// the same number of leading zero bits. Kept oldest-first so eviction can
+/// prefer the longest-lived entry.
+struct Bucket {
+    peers: VecDeque<Peer>,
+    capacity: usize,
+}
+
+impl Bucket {
+    fn new(capacity: usize) -> Self {
+        Self {
+            peers: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Inserts `peer`, refreshing its position if already present. When
+    /// the bucket is full, evicts the oldest entry only if `is_responsive`
+    /// reports it's gone stale; otherwise `peer` is dropped, keeping the
+    /// longest-lived responsive peer in place.
+    fn insert(&mut self, peer: Peer, is_responsive: impl Fn(Connection) -> bool) {
+        if let Some(pos) = self.peers.iter().position(|p| p.connection == peer.connection) {
+            self.peers.remove(pos);
+            self.peers.push_back(peer);
+            return;
+        }
+
+        if self.peers.len() < self.capacity {
+            self.peers.push_back(peer);
+            return;
+        }
+
+        if let Some(oldest) = self.peers.front() {
+            if !is_responsive(oldest.connection) {
+                self.peers.pop_front();
+                self.peers.push_back(peer);
+            }
+        }
+    }
+}
+
+/// Tracks recent liveness checks for connections known to the routing
+/// table, so `find_closest` can prefer peers known to be up over ones that
+/// have never been probed.
+pub struct LivenessTracker {
+    responsive: ConnectionDataList<bool>,
+}
+
+impl LivenessTracker {
+    pub fn new(size: usize) -> Self {
+        Self {
+            responsive: ConnectionDataList::new(size),
+        }
+    }
+
+    pub fn mark_responsive(&mut self, connection: Connection) {
+        self.responsive.set(connection, true);
+    }
+
+    pub fn mark_unresponsive(&mut self, connection: Connection) {
+        self.responsive.set(connection, false);
+    }
+
+    pub fn is_responsive(&self, connection: Connection) -> bool {
+        self.responsive.get(connection).copied().unwrap_or(false)
+    }
+}
+
+/// A Kademlia-style distance routing table over connection ids. Known
+/// peers are organized into distance buckets relative to our own key, so
+/// `netstack` can support peer lookup and gossip fan-out rather than only
+/// tracking an opaque flat slot list.
+pub struct RoutingTable {
+    own_key: NodeKey,
+    buckets: Vec<Bucket>,
+}
+
+impl RoutingTable {
+    pub fn new(own_key: NodeKey, bucket_capacity: usize) -> Self {
+        let buckets = (0..NUM_BUCKETS).map(|_| Bucket::new(bucket_capacity)).collect();
+
+        Self { own_key, buckets }
+    }
+
+    fn bucket_index(&self, key: &NodeKey) -> usize {
+        let distance = self.own_key.distance(key);
+        leading_zero_bits(&distance).min(NUM_BUCKETS - 1)
+    }
+
+    /// Learns about (or refreshes) a peer at `key`, routing it into the
+    /// bucket for its distance from our own key.
+    pub fn insert(&mut self, connection: Connection, key: NodeKey, liveness: &LivenessTracker) {
+        let index = self.bucket_index(&key);
+        let peer = Peer { connection, key };
+
+        self.buckets[index].insert(peer, |c| liveness.is_responsive(c));
+    }
+
+    pub fn remove(&mut self, connection: Connection) {
+        for bucket in &mut self.buckets {
+            bucket.peers.retain(|peer| peer.connection != connection);
+        }
+    }
+
+    /// Returns up to `count` known connections whose key is closest to
+    /// `target` by XOR distance. Among equally distant peers, those that
+    /// have recently passed a liveness check rank ahead of untested ones;
+    /// liveness never outranks a smaller distance. `filter` restricts
+    /// results to connections meeting some capability requirement. Passing `include_self` additionally
+    /// folds our own node into the ranking at its distance from `target`.
+    pub fn find_closest(
+        &self,
+        target: NodeKey,
+        count: usize,
+        include_self: Option<Connection>,
+        liveness: &LivenessTracker,
+        filter: impl Fn(Connection) -> bool,
+    ) -> Vec<Connection> {
+        let mut candidates: Vec<(Connection, [u8; KEY_LEN], bool)> = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.peers.iter())
+            .filter(|peer| filter(peer.connection))
+            .map(|peer| {
+                let distance = target.distance(&peer.key);
+                (peer.connection, distance, liveness.is_responsive(peer.connection))
+            })
+            .collect();
+
+        if let Some(own) = include_self {
+            if filter(own) {
+                candidates.push((own, target.distance(&self.own_key), true));
+            }
+        }
+
+        // XOR distance to `target` is the primary ranking key; liveness only
+        // breaks ties between otherwise equally-close peers
+        candidates.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| b.2.cmp(&a.2)));
+
+        candidates
+            .into_iter()
+            .take(count)
+            .map(|(connection, _, _)| connection)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionList;
+
+    fn key(first_byte: u8) -> NodeKey {
+        let mut bytes = [0u8; KEY_LEN];
+        bytes[0] = first_byte;
+        NodeKey::from_bytes(bytes)
+    }
+
+    #[test]
+    fn find_closest_ranks_by_distance_before_liveness() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut table = RoutingTable::new(key(0), 20);
+        let mut liveness = LivenessTracker::new(4);
+
+        let near = connections.create_connection().unwrap();
+        let far = connections.create_connection().unwrap();
+
+        table.insert(near, key(0b0000_0001), &liveness);
+        table.insert(far, key(0b1000_0000), &liveness);
+
+        // only the distant peer has passed a liveness check; distance must
+        // still win
+        liveness.mark_responsive(far);
+
+        let closest = table.find_closest(key(0), 1, None, &liveness, |_| true);
+
+        assert_eq!(closest, vec![near]);
+    }
+
+    #[test]
+    fn find_closest_uses_liveness_only_to_break_distance_ties() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut table = RoutingTable::new(key(0), 20);
+        let mut liveness = LivenessTracker::new(4);
+
+        let untested = connections.create_connection().unwrap();
+        let responsive = connections.create_connection().unwrap();
+
+        // equidistant from the target
+        table.insert(untested, key(5), &liveness);
+        table.insert(responsive, key(5), &liveness);
+        liveness.mark_responsive(responsive);
+
+        let closest = table.find_closest(key(0), 1, None, &liveness, |_| true);
+
+        assert_eq!(closest, vec![responsive]);
+    }
+
+    #[test]
+    fn find_closest_excludes_connections_rejected_by_the_filter() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut table = RoutingTable::new(key(0), 20);
+        let liveness = LivenessTracker::new(4);
+
+        let excluded = connections.create_connection().unwrap();
+        table.insert(excluded, key(1), &liveness);
+
+        let closest = table.find_closest(key(0), 1, None, &liveness, |c| c != excluded);
+
+        assert!(closest.is_empty());
+    }
+}
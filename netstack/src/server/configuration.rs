@@ -2,11 +2,21 @@ use crate::security::Secret;
 
 pub struct Configuration {
     pub max_connections: usize,
+    /// Caps how many connections (reserved or live) a single remote address
+    /// may hold at once, so one remote can't exhaust `max_connections`
+    pub max_per_remote: usize,
+    /// Bound on how many remotes may queue in the reservation waiter queue
+    /// while the pool is full
+    pub max_waiters: usize,
     /// Timeout after `timeout` updates, should be several lengths of `heartbeat`
     pub timeout: usize,
     pub reserved_timeout: usize,
     /// Send a heartbeat message every `heartbeat` updates
     pub heartbeat: usize,
+    /// Number of times to retransmit a path-validation challenge before
+    /// giving up on a candidate migration path, paced on the same per-update
+    /// cadence as `heartbeat`/`timeout`
+    pub path_challenge_retransmits: usize,
     /// Allows all connections with a given secret
     pub allow_all: Option<Secret>,
 }
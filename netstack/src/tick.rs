@@ -0,0 +1,198 @@
+use crate::connection::{Connection, ConnectionDataList, ConnectionList, Reservation};
+use crate::server::configuration::Configuration;
+
+struct TickState {
+    idle: usize,
+}
+
+/// The connections/reservations that a single `tick` identified as needing
+/// action.
+pub struct TickResult {
+    /// Live connections whose idle counter exceeded `timeout`. These have
+    /// already been removed from the `ConnectionList` passed to `tick`.
+    pub timed_out: Vec<Connection>,
+    /// Live connections whose idle counter is a multiple of `heartbeat`
+    /// this cycle and should have a heartbeat sent.
+    pub heartbeats: Vec<Connection>,
+    /// Reservations that weren't promoted within `reserved_timeout` and
+    /// have been released back to the pool (or handed straight to a
+    /// waiter).
+    pub reclaimed_reservations: Vec<Reservation>,
+}
+
+/// Drives `Configuration::timeout`/`reserved_timeout`/`heartbeat` by
+/// tracking a per-connection idle counter that advances once per `tick`
+/// and resets whenever traffic is seen, alongside a parallel idle counter
+/// for reservations that haven't been promoted yet.
+pub struct TickEngine {
+    state: ConnectionDataList<TickState>,
+    reservations: Vec<(Reservation, usize)>,
+}
+
+impl TickEngine {
+    pub fn new(size: usize) -> Self {
+        Self {
+            state: ConnectionDataList::new(size),
+            reservations: Vec::new(),
+        }
+    }
+
+    /// Starts tracking an established `connection` against `timeout`.
+    pub fn track(&mut self, connection: Connection) {
+        self.state.set(connection, TickState { idle: 0 });
+    }
+
+    /// Starts tracking a reservation against `reserved_timeout`; if it
+    /// isn't promoted in time, `tick` releases it back to the pool.
+    pub fn track_reservation(&mut self, reservation: Reservation) {
+        self.reservations.push((reservation, 0));
+    }
+
+    /// Stops tracking a reservation, e.g. because it was just promoted.
+    pub fn untrack_reservation(&mut self, reservation: Reservation) {
+        self.reservations.retain(|(tracked, _)| *tracked != reservation);
+    }
+
+    /// Resets the idle counter for `connection`. Call whenever traffic is
+    /// seen on it.
+    pub fn on_traffic(&mut self, connection: Connection) {
+        if let Some(state) = self.state.get_mut(connection) {
+            state.idle = 0;
+        }
+    }
+
+    /// Advances every live connection and pending reservation by one
+    /// update.
+    pub fn tick(&mut self, connections: &mut ConnectionList, config: &Configuration) -> TickResult {
+        let live: Vec<Connection> = (&*connections).into_iter().collect();
+
+        let mut timed_out = Vec::new();
+        let mut heartbeats = Vec::new();
+
+        for connection in live {
+            let state = match self.state.get_mut(connection) {
+                Some(state) => state,
+                None => continue,
+            };
+
+            state.idle += 1;
+
+            if state.idle > config.timeout {
+                timed_out.push(connection);
+                continue;
+            }
+
+            if config.heartbeat != 0 && state.idle % config.heartbeat == 0 {
+                heartbeats.push(connection);
+            }
+        }
+
+        for &connection in &timed_out {
+            let _ = connections.delete_connection(connection);
+            self.state.remove(connection);
+        }
+
+        let mut reclaimed_reservations = Vec::new();
+        self.reservations.retain_mut(|(reservation, idle)| {
+            *idle += 1;
+
+            if *idle > config.reserved_timeout {
+                connections.release(*reservation);
+                reclaimed_reservations.push(*reservation);
+                false
+            } else {
+                true
+            }
+        });
+
+        TickResult {
+            timed_out,
+            heartbeats,
+            reclaimed_reservations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionList;
+    use crate::server::configuration::Configuration;
+
+    fn config(timeout: usize, reserved_timeout: usize, heartbeat: usize) -> Configuration {
+        Configuration {
+            max_connections: 4,
+            max_per_remote: usize::MAX,
+            max_waiters: 0,
+            timeout,
+            reserved_timeout,
+            heartbeat,
+            path_challenge_retransmits: 0,
+            allow_all: None,
+        }
+    }
+
+    #[test]
+    fn connection_times_out_once_idle_exceeds_timeout() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut tick = TickEngine::new(4);
+        let cfg = config(2, 2, 0);
+
+        let conn = connections.create_connection().unwrap();
+        tick.track(conn);
+
+        assert!(tick.tick(&mut connections, &cfg).timed_out.is_empty());
+        assert!(tick.tick(&mut connections, &cfg).timed_out.is_empty());
+
+        let result = tick.tick(&mut connections, &cfg);
+        assert_eq!(result.timed_out, vec![conn]);
+        assert!(!connections.is_alive(conn));
+    }
+
+    #[test]
+    fn traffic_resets_the_idle_counter() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut tick = TickEngine::new(4);
+        let cfg = config(2, 2, 0);
+
+        let conn = connections.create_connection().unwrap();
+        tick.track(conn);
+
+        tick.tick(&mut connections, &cfg);
+        tick.on_traffic(conn);
+        let result = tick.tick(&mut connections, &cfg);
+
+        assert!(result.timed_out.is_empty());
+        assert!(connections.is_alive(conn));
+    }
+
+    #[test]
+    fn heartbeat_fires_on_multiples_of_the_configured_cadence() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut tick = TickEngine::new(4);
+        let cfg = config(10, 10, 2);
+
+        let conn = connections.create_connection().unwrap();
+        tick.track(conn);
+
+        assert!(tick.tick(&mut connections, &cfg).heartbeats.is_empty());
+        assert_eq!(tick.tick(&mut connections, &cfg).heartbeats, vec![conn]);
+    }
+
+    #[test]
+    fn unpromoted_reservation_is_reclaimed_after_reserved_timeout() {
+        let mut connections = ConnectionList::new(4, usize::MAX, 0);
+        let mut tick = TickEngine::new(4);
+        let cfg = config(10, 1, 0);
+
+        let addr: std::net::SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let reservation = connections.reserve(addr).unwrap();
+        tick.track_reservation(reservation);
+
+        assert!(tick.tick(&mut connections, &cfg).reclaimed_reservations.is_empty());
+
+        let result = tick.tick(&mut connections, &cfg);
+        assert_eq!(result.reclaimed_reservations, vec![reservation]);
+        assert!(connections.promote(reservation).is_none());
+    }
+}